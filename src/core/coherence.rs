@@ -0,0 +1,166 @@
+// Data-driven coherence/language scoring, adapted from charset-normalizer-rs's
+// `cd.rs`. Two pieces:
+//
+// - `encoding_unicode_range` characterizes what scripts a *candidate encoding*
+//   can produce, by decoding every byte 0x40..=0xFF through it and histogramming the
+//   resulting Unicode ranges.
+// - `alphabet_languages` characterizes what scripts the *decoded text* is
+//   actually using, by comparing the character set present against bundled
+//   per-language frequency tables.
+//
+// Together these replace the old hardcoded `detect_language_hints`, which
+// only knew about four languages, with something that scales to as many
+// languages as we bundle a profile for.
+
+/// Frequency-ordered (most common first) character sets for a handful of
+/// languages, used to rank how well a decoded text's alphabet matches each.
+struct LanguageProfile {
+    name: &'static str,
+    chars: &'static str,
+}
+
+const LANGUAGE_PROFILES: &[LanguageProfile] = &[
+    LanguageProfile { name: "English", chars: "etaoinshrdlucmfwypvbgkjqxz" },
+    LanguageProfile { name: "French", chars: "esaitnrulodcpmévqfbghjxyzàèêâûôîçù" },
+    LanguageProfile { name: "Portuguese", chars: "aeosrindmtculpgvhbçãõáéíóú" },
+    LanguageProfile { name: "Spanish", chars: "eaosrnidlctumpbgvyqhfzjñáéíóú" },
+    LanguageProfile { name: "German", chars: "enisratdhulcgmobwfkzvpäöüß" },
+    LanguageProfile { name: "Italian", chars: "eaiotnrlscdupmvgbfhzqàèéìòù" },
+    LanguageProfile { name: "Russian", chars: "оеаинтслврмкдпуяыьгзбчйхжшюц" },
+    LanguageProfile { name: "Ukrainian", chars: "оаниітврслкдмупяєгзбйчхцжющїь" },
+    LanguageProfile { name: "Greek", chars: "ατοηενισρκμυπωδγλχθβφξζ" },
+    LanguageProfile { name: "Turkish", chars: "aeinrlıdkmuytsboüşgzcçhpğvfjwxq" },
+    LanguageProfile { name: "Arabic", chars: "ايلمنوهرتبكعفسدقجحصخشذطزثضظغ" },
+    LanguageProfile { name: "Hebrew", chars: "יוהלאמנרבתשדחקעכגפצסטזך" },
+    LanguageProfile { name: "Korean", chars: "이다에의는로을를가지하고것들" },
+];
+
+/// Name of the Unicode block/range a single character falls in, grouped
+/// coarsely enough to be useful as a per-encoding histogram bucket.
+fn unicode_range_name(c: char) -> &'static str {
+    let code = c as u32;
+    match code {
+        0x0000..=0x007F => "Basic Latin",
+        0x0080..=0x00FF => "Latin-1 Supplement",
+        0x0100..=0x024F => "Latin Extended",
+        0x0370..=0x03FF => "Greek and Coptic",
+        0x0400..=0x04FF => "Cyrillic",
+        0x0530..=0x058F => "Armenian",
+        0x0590..=0x05FF => "Hebrew",
+        0x0600..=0x06FF => "Arabic",
+        0x0900..=0x097F => "Devanagari",
+        0x3040..=0x309F => "Hiragana",
+        0x30A0..=0x30FF => "Katakana",
+        0x4E00..=0x9FFF => "CJK Unified Ideographs",
+        0xAC00..=0xD7AF => "Hangul Syllables",
+        _ => "Other",
+    }
+}
+
+/// Minimum share of mapped high bytes a Unicode range must account for to be
+/// considered a range the encoding meaningfully produces.
+const RANGE_FREQUENCY_THRESHOLD: f32 = 0.15;
+
+/// Characterize what scripts `iana_name` can produce by decoding every byte
+/// 0x40..=0xFF through it and histogramming the resulting Unicode ranges.
+/// Returns the ranges whose share of mapped bytes exceeds the threshold,
+/// most frequent first.
+pub fn encoding_unicode_range(iana_name: &str) -> Vec<&'static str> {
+    // Stateful escape-sequence encodings (ISO-2022-JP) don't produce
+    // anything meaningful from single isolated bytes — decoding resets to
+    // ASCII state on every call, so a byte-by-byte probe only ever sees
+    // "Basic Latin" and the scripts reachable via escape designators
+    // never show up. Report their real repertoire directly instead.
+    if iana_name.eq_ignore_ascii_case("ISO-2022-JP") {
+        return vec!["Basic Latin", "Hiragana", "Katakana", "CJK Unified Ideographs"];
+    }
+
+    let encoding = match encoding_rs::Encoding::for_label(iana_name.as_bytes()) {
+        Some(e) => e,
+        None => return Vec::new(),
+    };
+
+    let mut histogram: std::collections::HashMap<&'static str, usize> = std::collections::HashMap::new();
+    let mut total = 0usize;
+
+    for byte in 0x40u8..=0xFF {
+        let bytes = [byte];
+        let (decoded, _, had_errors) = encoding.decode(&bytes);
+        if had_errors {
+            continue;
+        }
+        if let Some(c) = decoded.chars().next() {
+            *histogram.entry(unicode_range_name(c)).or_insert(0) += 1;
+            total += 1;
+        }
+    }
+
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut ranges: Vec<(&'static str, usize)> = histogram.into_iter().collect();
+    ranges.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    ranges
+        .into_iter()
+        .filter(|(_, count)| *count as f32 / total as f32 > RANGE_FREQUENCY_THRESHOLD)
+        .map(|(range, _)| range)
+        .collect()
+}
+
+/// Characterize what scripts already-decoded `text` actually uses, with the
+/// same range bucketing and frequency threshold as `encoding_unicode_range`,
+/// so the two are directly comparable. "Basic Latin" is dropped from the
+/// result since it's near-universal (plain ASCII, punctuation, digits) and
+/// carries no disambiguating signal against a candidate encoding's repertoire.
+pub fn text_unicode_ranges(text: &str) -> Vec<&'static str> {
+    let mut histogram: std::collections::HashMap<&'static str, usize> = std::collections::HashMap::new();
+    let mut total = 0usize;
+
+    for c in text.chars() {
+        if c.is_whitespace() || c.is_ascii_punctuation() || c.is_ascii_digit() {
+            continue;
+        }
+        *histogram.entry(unicode_range_name(c)).or_insert(0) += 1;
+        total += 1;
+    }
+
+    if total == 0 {
+        return Vec::new();
+    }
+
+    histogram
+        .into_iter()
+        .filter(|(range, count)| *range != "Basic Latin" && *count as f32 / total as f32 > RANGE_FREQUENCY_THRESHOLD)
+        .map(|(range, _)| range)
+        .collect()
+}
+
+/// Rank candidate languages by how much of the decoded text's alphabet
+/// overlaps with each language's frequent-character profile. Returns
+/// `(language, coherence_ratio)` pairs sorted best-first.
+pub fn alphabet_languages(text: &str) -> Vec<(&'static str, f32)> {
+    let present: std::collections::HashSet<char> = text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+
+    if present.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ranked: Vec<(&'static str, f32)> = LANGUAGE_PROFILES
+        .iter()
+        .map(|profile| {
+            let matched = profile.chars.chars().filter(|c| present.contains(c)).count();
+            let ratio = matched as f32 / profile.chars.chars().count() as f32;
+            (profile.name, ratio)
+        })
+        .filter(|(_, ratio)| *ratio > 0.0)
+        .collect();
+
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
@@ -0,0 +1,120 @@
+// Hand-rolled HZ-GB2312 decoder. `encoding_rs` only implements the WHATWG
+// Encoding Standard, which dropped HZ-GB2312 (along with ISO-2022-KR/CN)
+// years ago — `Encoding::for_label` maps the name to the `replacement`
+// encoding, which errors on any non-empty input, so there is no codec to
+// decode through there. HZ is simple enough (a 7-bit-safe stream that shifts
+// between ASCII and GB2312 double-byte mode via `~{`/`~}` escapes) that it's
+// cheaper to decode directly than to pull in a dedicated crate for it.
+
+/// Decode a HZ-GB2312 byte stream, or `None` if it doesn't parse as valid HZ:
+/// a stray high bit, an unterminated shift, an odd number of bytes inside a
+/// shifted run, or a GB2312 code point with no Unicode mapping.
+pub fn decode(buffer: &[u8]) -> Option<String> {
+    let mut out = String::new();
+    let mut shifted = false;
+    let mut pending: Option<u8> = None;
+    let mut i = 0;
+
+    while i < buffer.len() {
+        let b = buffer[i];
+
+        // HZ is strictly 7-bit; a high bit means this isn't HZ at all.
+        if b >= 0x80 {
+            return None;
+        }
+
+        if !shifted && b == b'~' {
+            match buffer.get(i + 1) {
+                Some(b'{') => {
+                    shifted = true;
+                    i += 2;
+                }
+                Some(b'~') => {
+                    out.push('~');
+                    i += 2;
+                }
+                Some(b'\n') => {
+                    // Line-continuation escape: consumes the newline, no output.
+                    i += 2;
+                }
+                _ => return None,
+            }
+            continue;
+        }
+
+        if shifted && b == b'~' && buffer.get(i + 1) == Some(&b'}') {
+            if pending.is_some() {
+                return None; // shift-out in the middle of a double-byte pair
+            }
+            shifted = false;
+            i += 2;
+            continue;
+        }
+
+        if shifted {
+            if !(0x21..=0x7E).contains(&b) {
+                return None;
+            }
+
+            match pending.take() {
+                None => pending = Some(b),
+                Some(lead) => {
+                    let gb_bytes = [lead | 0x80, b | 0x80];
+                    let (decoded, _, had_errors) = encoding_rs::GBK.decode(&gb_bytes);
+                    if had_errors {
+                        return None;
+                    }
+                    out.push_str(&decoded);
+                }
+            }
+            i += 1;
+            continue;
+        }
+
+        out.push(b as char);
+        i += 1;
+    }
+
+    if shifted || pending.is_some() {
+        return None; // unterminated shift or dangling half of a pair
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifted_pair_round_trips_to_the_right_character() {
+        // 0xD6 0xD0 is the GBK encoding of '中'; HZ carries each byte with
+        // its high bit masked off inside a `~{`/`~}` shifted run.
+        assert_eq!(decode(b"~{VP~}"), Some("中".to_string()));
+    }
+
+    #[test]
+    fn unterminated_shift_is_rejected() {
+        assert_eq!(decode(b"~{VP"), None);
+    }
+
+    #[test]
+    fn dangling_half_pair_before_shift_out_is_rejected() {
+        assert_eq!(decode(b"~{V~}"), None);
+    }
+
+    #[test]
+    fn literal_tilde_escape_is_unescaped() {
+        assert_eq!(decode(b"a~~b"), Some("a~b".to_string()));
+    }
+
+    #[test]
+    fn line_continuation_escape_consumes_the_newline() {
+        assert_eq!(decode(b"a~\nb"), Some("ab".to_string()));
+    }
+
+    #[test]
+    fn byte_outside_the_shifted_range_is_rejected() {
+        assert_eq!(decode(b"~{\x01\x01~}"), None);
+    }
+}
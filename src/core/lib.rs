@@ -1,8 +1,17 @@
 use pyo3::prelude::*;
-use pyo3::exceptions::PyIOError;
+use pyo3::exceptions::{PyIOError, PyValueError};
 use std::fs::File;
 use std::io::{Read, BufReader};
 use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+
+mod coherence;
+mod hz_gb2312;
+mod matches;
+mod mess;
+mod scoring;
+
+pub(crate) use matches::{CharsetMatches, CharsetMatchesIter};
 
 // Constantes para controle de memória
 const CHUNK_SIZE: usize = 8192; // 8KB por chunk
@@ -36,71 +45,135 @@ fn normalize_encoding_name(encoding: &str) -> String {
         "mac_cyrillic" | "x_mac_cyrillic" => "mac_cyrillic".to_string(),
         "koi8_r" | "koi8r" => "koi8_r".to_string(),
         "koi8_u" => "koi8_u".to_string(),
+        "iso_2022_jp" => "iso2022_jp".to_string(),
+        "hz_gb2312" | "hz_gb_2312" | "hz" => "hz".to_string(),
         other if other.starts_with("cp_") => other.replace("_", ""),
         other => other.to_string(),
     }
 }
 
+/// Map a Python-codec-style name (as produced by `normalize_encoding_name`,
+/// or typed by a caller) to a WHATWG label `encoding_rs::Encoding::for_label`
+/// actually recognizes. The two naming schemes mostly agree, but diverge for
+/// names like `utf_8`/`utf_16le`/`latin_1`/`cp949` where the underscored
+/// Python form isn't a registered WHATWG alias.
+fn codec_name_to_whatwg_label(encoding: &str) -> String {
+    match normalize_encoding_name(encoding).as_str() {
+        "utf_8" => "utf-8".to_string(),
+        "utf_16" | "utf_16le" => "utf-16le".to_string(),
+        "utf_16be" => "utf-16be".to_string(),
+        "latin_1" => "iso-8859-1".to_string(),
+        "cp949" => "euc-kr".to_string(),
+        "mac_roman" => "macintosh".to_string(),
+        "mac_cyrillic" => "x-mac-cyrillic".to_string(),
+        "koi8_u" => "koi8-u".to_string(),
+        "iso2022_jp" => "iso-2022-jp".to_string(),
+        other => other.replace('_', "-"),
+    }
+}
+
 /// CharsetMatch represents a single encoding detection result
 #[pyclass]
 #[derive(Clone)]
-struct CharsetMatch {
+pub(crate) struct CharsetMatch {
     #[pyo3(get)]
     encoding: String,
+    #[pyo3(get)]
+    raw_score: i64,
+    #[pyo3(get)]
+    language: String,
+    #[pyo3(get)]
+    chaos: f32,
+    #[pyo3(get)]
+    coherence: f32,
+    /// Other encodings among the candidates tried that decoded these exact
+    /// same bytes into identical text (e.g. windows-1252 vs windows-1250 when
+    /// every non-ASCII byte maps to the same letter in both), by their
+    /// `encoding_rs` names. Valid UTF-8 input without a BOM never reaches
+    /// this candidate list at all — it's returned directly as a single
+    /// `utf_8` match; BOM-prefixed input is scored like any other buffer.
+    #[pyo3(get)]
+    submatch: Vec<String>,
+    /// Other encodings that passed the mess filter and could plausibly also
+    /// explain this buffer, ranked below this match.
+    #[pyo3(get)]
+    could_be_from_charset: Vec<String>,
+    /// Whether a BOM was present (and stripped) on the original input.
+    #[pyo3(get)]
+    has_bom: bool,
     raw_bytes: Vec<u8>,
     decoded_text: String,
 }
 
+/// The BOM bytes `encoding` expects at the start of a stream, or `&[]` for
+/// encodings that don't have one.
+fn bom_bytes_for(encoding: &'static encoding_rs::Encoding) -> &'static [u8] {
+    match encoding.name() {
+        "UTF-8" => &[0xEF, 0xBB, 0xBF],
+        "UTF-16LE" => &[0xFF, 0xFE],
+        "UTF-16BE" => &[0xFE, 0xFF],
+        _ => &[],
+    }
+}
+
 #[pymethods]
 impl CharsetMatch {
     fn __str__(&self) -> PyResult<String> {
         Ok(self.decoded_text.clone())
     }
 
-    fn __repr__(&self) -> PyResult<String> {
-        Ok(format!("<CharsetMatch '{}' bytes ({})>", self.encoding, self.raw_bytes.len()))
-    }
-}
-
-// Analyze byte patterns to detect likely encoding type
-fn analyze_byte_patterns(buffer: &[u8]) -> Vec<&'static str> {
-    let mut hints = Vec::new();
-
-    // Count byte ranges
-    let high_bytes = buffer.iter().filter(|&&b| b >= 0x80).count();
-    if high_bytes == 0 {
-        return hints; // Pure ASCII
-    }
-
-    let total_len = buffer.len() as f32;
-
-    // Byte distribution analysis
-    let lower_high = buffer.iter().filter(|&&b| b >= 0xC0 && b < 0xE0).count();
-    let upper_high = buffer.iter().filter(|&&b| b >= 0xE0).count();
-    let arabic_specific = buffer.iter().filter(|&&b| b >= 0xC0 && b <= 0xE5).count();
+    /// Re-encode `decoded_text` into `encoding` (`utf_8` by default). NFC
+    /// normalization is applied first unless `normalize=False`, so that text
+    /// which reached us as precomposed under one source encoding and
+    /// decomposed under another still compares equal. The BOM that may have
+    /// been stripped from the input is not reintroduced unless
+    /// `include_bom=True`. `encoding` is accepted in either Python-codec
+    /// style (`utf_8`, `latin_1`, `cp949`) or WHATWG style (`utf-8`,
+    /// `iso-8859-1`) and mapped to whatever label `encoding_rs` needs.
+    /// HZ-GB2312 has no `encoding_rs` encoder, so `hz` can be decoded into
+    /// but never output to.
+    #[pyo3(signature = (encoding=None, normalize=true, include_bom=false))]
+    fn output(&self, encoding: Option<String>, normalize: bool, include_bom: bool) -> PyResult<Vec<u8>> {
+        let target = encoding.unwrap_or_else(|| "utf_8".to_string());
+        let target_label = codec_name_to_whatwg_label(&target);
+        let target_encoding = encoding_rs::Encoding::for_label(target_label.as_bytes())
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown encoding: {}", target)))?;
+
+        let text = if normalize {
+            self.decoded_text.nfc().collect::<String>()
+        } else {
+            self.decoded_text.clone()
+        };
 
-    let lower_ratio = lower_high as f32 / total_len;
-    let upper_ratio = upper_high as f32 / total_len;
-    let arabic_ratio = arabic_specific as f32 / total_len;
+        // `Encoding::encode` writes UTF-8 for the UTF-16 encodings, so those
+        // are encoded by hand.
+        let mut bytes: Vec<u8> = match target_encoding.name() {
+            "UTF-16LE" => text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect(),
+            "UTF-16BE" => text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect(),
+            _ => target_encoding.encode(&text).0.into_owned(),
+        };
 
-    // Turkish specific bytes
-    let turkish_specific = buffer.iter().filter(|&&b| matches!(b, 0xF0 | 0xFD | 0xFE)).count();
+        if include_bom {
+            let mut with_bom = bom_bytes_for(target_encoding).to_vec();
+            with_bom.append(&mut bytes);
+            bytes = with_bom;
+        }
 
-    // Mac Cyrillic has very high concentration (>60%) in upper range (0xE0-0xFF)
-    // while Arabic spreads more evenly
-    if upper_ratio > 0.55 && lower_ratio < 0.35 {
-        hints.push("likely_mac_cyrillic");
+        Ok(bytes)
     }
-    // Arabic has good spread in 0xC0-0xE5 but not too much upper concentration
-    else if arabic_ratio > 0.35 && upper_ratio < 0.65 {
-        hints.push("likely_arabic");
+
+    /// Shorthand for `output()` with the default UTF-8 target.
+    fn bytes(&self) -> PyResult<Vec<u8>> {
+        self.output(None, true, false)
     }
 
-    if turkish_specific >= 2 {
-        hints.push("likely_turkish");
+    fn __bytes__(&self) -> PyResult<Vec<u8>> {
+        self.output(None, true, false)
     }
 
-    hints
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("<CharsetMatch '{}' bytes ({})>", self.encoding, self.raw_bytes.len()))
+    }
 }
 
 // Detect UTF-16 by analyzing null byte patterns
@@ -128,80 +201,50 @@ fn detect_utf16_pattern(buffer: &[u8]) -> Option<&'static str> {
     None
 }
 
-// Detect language characteristics from decoded text
-fn detect_language_hints(text: &str) -> Vec<&'static str> {
-    let mut hints = Vec::new();
-
-    let total_chars = text.chars().count().max(1);
-
-    let arabic_chars = text.chars().filter(|c| {
-        let code = *c as u32;
-        // Arabic block + Arabic Presentation Forms
-        (code >= 0x0600 && code <= 0x06FF) ||
-        (code >= 0xFB50 && code <= 0xFDFF) ||
-        (code >= 0xFE70 && code <= 0xFEFF)
-    }).count();
-
-    let cyrillic_chars = text.chars().filter(|c| {
-        let code = *c as u32;
-        (code >= 0x0400 && code <= 0x04FF) ||
-        (code >= 0x0500 && code <= 0x052F)
-    }).count();
-
-    let turkish_specific = text.chars().filter(|c| {
-        // Turkish-specific letters that don't appear in other Latin scripts
-        matches!(*c, 'ğ' | 'Ğ' | 'ı' | 'İ' | 'ş' | 'Ş')
-    }).count();
-
-    let korean_chars = text.chars().filter(|c| {
-        let code = *c as u32;
-        // Hangul Syllables + Hangul Jamo
-        (code >= 0xAC00 && code <= 0xD7AF) ||
-        (code >= 0x1100 && code <= 0x11FF) ||
-        (code >= 0x3130 && code <= 0x318F)
-    }).count();
-
-    // Calculate percentages
-    let arabic_ratio = arabic_chars as f32 / total_chars as f32;
-    let cyrillic_ratio = cyrillic_chars as f32 / total_chars as f32;
-    let korean_ratio = korean_chars as f32 / total_chars as f32;
-
-    // Arabic text typically has high ratio of Arabic characters
-    if arabic_ratio > 0.3 {
-        hints.push("arabic");
-    }
-    // Cyrillic, but not if there's more Arabic
-    if cyrillic_ratio > 0.2 && arabic_ratio < 0.1 {
-        hints.push("cyrillic");
-    }
-    // Turkish needs at least a few specific chars
-    if turkish_specific >= 3 {
-        hints.push("turkish");
-    }
-    // Korean text has very high ratio of Korean chars
-    if korean_ratio > 0.2 {
-        hints.push("korean");
-    }
-
-    hints
+/// Pick the best-matching language for already-decoded `text`, with its
+/// coherence ratio: the top-ranked bundled profile however narrow its lead,
+/// or an empty string and 0.0 if none of the text's letters appear in any
+/// profile.
+fn best_language(text: &str) -> (String, f32) {
+    match coherence::alphabet_languages(text).first() {
+        Some((name, ratio)) => (name.to_string(), *ratio),
+        None => (String::new(), 0.0),
+    }
 }
 
-/// Detects encoding and language from a file
-#[pyfunction]
-fn from_path(file_path: String) -> PyResult<CharsetMatch> {
-    // Read the file as bytes
-    let path = Path::new(&file_path);
-    let mut file = File::open(path).map_err(|e| {
-        PyIOError::new_err(format!("Failed to open file: {}", e))
-    })?;
+/// Designator/shift escape sequences that mark a stateful encoding. Unlike
+/// single/multi-byte encodings, these stay within the ASCII range (every byte
+/// round-trips through UTF-8 just fine), so plain UTF-8 validity can't be
+/// used to rule them out the way it does for everything else; they need
+/// their own prescan ahead of the UTF-8 check.
+const ISO_2022_JP_DESIGNATORS: &[&[u8]] = &[b"\x1b(B", b"\x1b(J", b"\x1b$@", b"\x1b$B"];
+
+/// Detect ISO-2022-JP or HZ-GB2312 by their escape/shift sequences. Requires
+/// every byte in the sample to be 7-bit, since a stray high byte alongside
+/// one of these sequences means it's not actually a stateful encoding (e.g. a
+/// literal "~{" in otherwise Latin-1 text).
+fn detect_stateful_escape_encoding(buffer: &[u8]) -> Option<&'static str> {
+    if buffer.iter().any(|&b| b >= 0x80) {
+        return None;
+    }
 
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).map_err(|e| {
-        PyIOError::new_err(format!("Failed to read file: {}", e))
-    })?;
+    if ISO_2022_JP_DESIGNATORS.iter().any(|seq| buffer.windows(seq.len()).any(|w| w == *seq)) {
+        return Some("iso-2022-jp");
+    }
+
+    if buffer.windows(2).any(|w| w == b"~{") && buffer.windows(2).any(|w| w == b"~}") {
+        return Some("HZ-GB2312");
+    }
+
+    None
+}
 
-    // Check for BOM markers
-    let (encoding_str, skip_bytes) = if buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
+/// An a priori guess at the encoding (from a BOM, a UTF-16 null-byte pattern,
+/// or a stateful escape sequence) plus how many leading bytes to skip (the
+/// BOM itself, if any). An empty guess means "no a priori signal; score
+/// every candidate".
+fn guess_initial_encoding(buffer: &[u8]) -> (&'static str, usize) {
+    if buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
         ("utf_8", 3)
     } else if buffer.starts_with(&[0xFF, 0xFE]) {
         ("UTF-16LE", 2)
@@ -211,69 +254,33 @@ fn from_path(file_path: String) -> PyResult<CharsetMatch> {
         ("UTF-32LE", 4)
     } else if buffer.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
         ("UTF-32BE", 4)
-    } else if let Some(utf16_encoding) = detect_utf16_pattern(&buffer) {
+    } else if let Some(utf16_encoding) = detect_utf16_pattern(buffer) {
         // Detected UTF-16 without BOM
         (utf16_encoding, 0)
+    } else if let Some(stateful_encoding) = detect_stateful_escape_encoding(buffer) {
+        // A priori only: the candidate still has to decode cleanly and clear
+        // the mess/score filter below like everything else, so a designator
+        // after which the rest of the sample doesn't decode cleanly (e.g. a
+        // lone `ESC $ B` in plain ASCII text) won't win on its own.
+        (stateful_encoding, 0)
+    } else if std::str::from_utf8(buffer).is_ok() {
+        // UTF-8 validity is the strongest possible signal: a byte stream that
+        // round-trips cleanly through UTF-8 essentially never coincidentally
+        // does so under another encoding, so accept it without scoring.
+        ("UTF-8", 0)
     } else {
-        // Analyze byte patterns before chardet
-        let byte_hints = analyze_byte_patterns(&buffer);
-
-        // Use chardet for initial detection
-        let result = chardet::detect(&buffer);
-        let detected = result.0.to_lowercase().replace("-", "_");
-
-        // Map chardet output to proper encoding names, considering byte hints
-        let encoding = match detected.as_str() {
-            "utf_8" | "utf8" | "ascii" => "UTF-8",
-            "big5" | "big_5" => "Big5",
-            "gb2312" | "gb_2312" | "gbk" => "GBK",
-            "windows_1252" | "cp1252" | "iso_8859_1" => {
-                // Check if it's actually Turkish
-                if byte_hints.contains(&"likely_turkish") {
-                    "windows-1254"
-                } else {
-                    "windows-1252"
-                }
-            },
-            "windows_1256" | "cp1256" | "iso_8859_6" => "windows-1256",
-            "windows_1255" | "cp1255" | "iso_8859_8" => "windows-1255",
-            "windows_1253" | "cp1253" | "iso_8859_7" => "windows-1253",
-            "windows_1251" | "cp1251" | "iso_8859_5" => {
-                // Check if it's actually Arabic or Mac Cyrillic
-                if byte_hints.contains(&"likely_arabic") {
-                    "windows-1256"
-                } else if byte_hints.contains(&"likely_mac_cyrillic") {
-                    "x-mac-cyrillic"
-                } else {
-                    "windows-1251"
-                }
-            },
-            "windows_1254" | "cp1254" | "iso_8859_9" => "windows-1254",
-            "windows_1250" | "cp1250" | "iso_8859_2" => "windows-1250",
-            "euc_kr" | "cp949" | "windows_949" | "ks_c_5601_1987" => {
-                // CP949 is a superset of EUC-KR and more commonly used
-                // If chardet detects EUC-KR, we prefer CP949
-                "windows-949"
-            },
-            "shift_jis" | "shift_jisx0213" | "cp932" => "shift_jis",
-            "euc_jp" => "EUC-JP",
-            "mac_cyrillic" | "x_mac_cyrillic" => "x-mac-cyrillic",
-            "koi8_r" | "koi8r" => "KOI8-R",
-            _ => "UTF-8", // fallback
-        };
-        (encoding, 0)
-    };
-
-    // Try to decode with detected encoding
-    let buffer_slice = &buffer[skip_bytes..];
-
-    // Build list of encodings to try, prioritizing the detected one
-    let mut encodings_to_try = vec![encoding_str];
+        ("", 0)
+    }
+}
 
-    // Get byte hints for prioritization
-    let byte_hints = analyze_byte_patterns(&buffer);
+/// The general fallback candidate list, prioritizing `encoding_str` (the a
+/// priori guess, if any) ahead of the strategically-ordered common set.
+fn build_candidate_list(encoding_str: &'static str) -> Vec<&'static str> {
+    let mut encodings_to_try: Vec<&str> = Vec::new();
+    if !encoding_str.is_empty() {
+        encodings_to_try.push(encoding_str);
+    }
 
-    // Add common encodings as fallbacks, with strategic ordering
     for enc in &[
         "UTF-8",
         "x-mac-cyrillic", // Higher priority for Mac Cyrillic
@@ -293,102 +300,281 @@ fn from_path(file_path: String) -> PyResult<CharsetMatch> {
         "mac-cyrillic",
         "KOI8-R",
         "ISO-8859-1",
+        "iso-2022-jp",
+        "HZ-GB2312",
     ] {
         if !encodings_to_try.contains(enc) {
             encodings_to_try.push(enc);
         }
     }
 
-    let mut best_encoding = None;
-    let mut best_text = String::new();
-    let mut min_error_ratio = 1.0;
-    let mut best_score = f32::MIN;
-
-    for encoding_name in &encodings_to_try {
-        if let Some(encoding) = encoding_rs::Encoding::for_label(encoding_name.as_bytes()) {
-            let (decoded, _, had_errors) = encoding.decode(buffer_slice);
-
-            // Calculate error ratio
-            let error_chars = decoded.chars().filter(|&c| c == '\u{FFFD}').count();
-            let total_chars = decoded.chars().count().max(1);
-            let error_ratio = error_chars as f32 / total_chars as f32;
+    encodings_to_try
+}
 
-            // Calculate a score based on multiple factors
-            let mut score = 1.0 - error_ratio;
+/// A single scored candidate decoding, before it's turned into the public
+/// `CharsetMatch` shape.
+struct ScoredCandidate {
+    name: String,
+    raw_score: i64,
+    mess: f32,
+    coherence: f32,
+    language: String,
+    text: String,
+}
 
-            // Bonus for detected encoding
-            if encoding_name == &encoding_str {
-                score += 0.05;
+/// Penalty applied when the decoded text uses a script that the candidate
+/// encoding's own byte repertoire (per `coherence::encoding_unicode_range`)
+/// can't plausibly produce at all — a strong signal the candidate is wrong
+/// even when the decode itself is clean and scores well otherwise.
+const SCRIPT_MISMATCH_PENALTY: i64 = -500;
+
+/// Score every candidate in `encodings_to_try` against `buffer_slice`,
+/// dropping anything whose mess ratio exceeds the threshold, and return the
+/// survivors sorted best-first (mess asc, then score desc, then coherence
+/// desc).
+fn score_candidates(buffer_slice: &[u8], encoding_str: &str, encodings_to_try: &[&str]) -> Vec<ScoredCandidate> {
+    let mut scored = Vec::new();
+
+    for encoding_name in encodings_to_try {
+        // HZ-GB2312 isn't in the WHATWG Encoding Standard, so `encoding_rs`
+        // has no codec for it (`for_label` maps the name to the `replacement`
+        // encoding, which errors on everything); it needs its own decoder.
+        let (resolved_name, decoded_text) = if encoding_name.eq_ignore_ascii_case("HZ-GB2312") {
+            match hz_gb2312::decode(buffer_slice) {
+                Some(text) => ("HZ-GB2312".to_string(), text),
+                None => continue,
             }
+        } else if let Some(encoding) = encoding_rs::Encoding::for_label(encoding_name.as_bytes()) {
+            let (decoded, _, had_errors) = encoding.decode(buffer_slice);
+            // A stateful encoding only counts if the whole sample decodes
+            // cleanly; the mess ratio doesn't count U+FFFD, so a broken
+            // ISO-2022-JP decode could otherwise beat the candidates that
+            // keep the raw escape byte.
+            if had_errors && encoding == encoding_rs::ISO_2022_JP {
+                continue;
+            }
+            (encoding.name().to_string(), decoded.to_string())
+        } else {
+            continue;
+        };
 
-            // Get language hints for this decoding
-            let lang_hints = detect_language_hints(&decoded);
+        // Several labels resolve to the same encoding (windows-949/EUC-KR,
+        // ISO-8859-1/windows-1252); score each encoding once.
+        if scored.iter().any(|c: &ScoredCandidate| c.name == resolved_name) {
+            continue;
+        }
 
-            // Strong bonus for language-specific encodings when we detect that language
-            if lang_hints.contains(&"arabic") && encoding_name.contains("1256") {
-                score += 0.5; // Very strong preference
-            }
-            if lang_hints.contains(&"turkish") && encoding_name.contains("1254") {
-                score += 0.4;
-            }
-            if lang_hints.contains(&"korean") {
-                // CP949 (windows-949) is a superset of EUC-KR and more commonly used
-                if encoding_name.contains("949") || encoding_name.contains("windows-949") {
-                    score += 0.4; // Strong preference for CP949
-                } else if encoding_name.contains("euc-kr") || encoding_name.contains("EUC-KR") {
-                    score += 0.2; // Lower preference for EUC-KR
-                }
-            }
-            if lang_hints.contains(&"cyrillic") {
-                if encoding_name.contains("mac-cyrillic") || encoding_name.contains("x-mac-cyrillic") {
-                    score += 0.5; // Strong preference for Mac Cyrillic
-                } else if encoding_name.contains("1251") {
-                    score += 0.2;
-                }
-            }
+        let raw_score = scoring::score_text(&decoded_text);
+        let mess = mess::mess_ratio(&decoded_text);
 
-            // Strong penalties for wrong language matches
-            if lang_hints.contains(&"arabic") && encoding_name.contains("1251") {
-                score -= 0.5;
-            }
-            if lang_hints.contains(&"cyrillic") && encoding_name.contains("1256") {
-                score -= 0.9; // Very strong penalty - Cyrillic text should never be Arabic
-            }
+        // A candidate that is mostly garbage, even if it happens to
+        // decode without replacement characters, is never the right one.
+        if mess > mess::MESS_THRESHOLD {
+            continue;
+        }
 
-            // Bonus for byte pattern hints
-            if byte_hints.contains(&"likely_mac_cyrillic") &&
-               (encoding_name.contains("mac-cyrillic") || encoding_name.contains("x-mac-cyrillic")) {
-                score += 0.4;
-            }
+        let (language, coherence) = best_language(&decoded_text);
 
-            if score > best_score || (score == best_score && error_ratio < min_error_ratio) {
-                best_score = score;
-                min_error_ratio = error_ratio;
-                best_encoding = Some(encoding.name().to_string());
-                best_text = decoded.to_string();
+        let mut score = raw_score;
+        // Bonus for the a priori detected encoding
+        if *encoding_name == encoding_str {
+            score += 50;
+        }
 
-                // If perfect decode with high score, stop searching
-                if !had_errors && error_ratio == 0.0 && score > 1.0 {
-                    break;
-                }
+        // What scripts can this encoding's byte repertoire produce at
+        // all, vs. what scripts the decode actually contains? A
+        // candidate whose repertoire can't explain an observed script
+        // (e.g. windows-1251 "decoding" Greek) is never the right one,
+        // even if the decode happens to be clean.
+        let producible_ranges = coherence::encoding_unicode_range(&resolved_name);
+        if !producible_ranges.is_empty() {
+            let observed_ranges = coherence::text_unicode_ranges(&decoded_text);
+            if observed_ranges.iter().any(|range| !producible_ranges.contains(range)) {
+                score += SCRIPT_MISMATCH_PENALTY;
             }
         }
+
+        scored.push(ScoredCandidate {
+            name: resolved_name,
+            raw_score: score,
+            mess,
+            coherence,
+            language,
+            text: decoded_text,
+        });
     }
 
-    let mut final_encoding = best_encoding.unwrap_or_else(|| "UTF-8".to_string());
+    // Mess is the primary tiebreaker, per the mess-ratio design: among
+    // survivors (already filtered to mess <= MESS_THRESHOLD above), the
+    // lowest-mess candidate wins even if a noisier one picked up a higher
+    // structural score. `raw_score` and then alphabet-language coherence
+    // only arbitrate candidates that are equally clean.
+    scored.sort_by(|a, b| {
+        a.mess
+            .partial_cmp(&b.mess)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| b.raw_score.cmp(&a.raw_score))
+            .then_with(|| b.coherence.partial_cmp(&a.coherence).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    scored
+}
 
+fn normalize_final_encoding(name: &str) -> String {
     // Post-processing: EUC-KR -> CP949 (CP949 is superset and more common)
-    if final_encoding.to_lowercase().contains("euc-kr") || final_encoding.to_lowercase().contains("euc_kr") {
-        final_encoding = "windows-949".to_string();
+    if name.to_lowercase().contains("euc-kr") || name.to_lowercase().contains("euc_kr") {
+        return normalize_encoding_name("windows-949");
     }
+    normalize_encoding_name(name)
+}
 
-    let normalized_encoding = normalize_encoding_name(&final_encoding);
+/// Run the full BOM-check -> candidate-list -> scoring pipeline over
+/// `buffer` and return the single best match. Shared by `from_path`,
+/// `from_path_stream`, `from_bytes` and `detect`, so all four entry points
+/// agree on exactly the same detection behavior.
+fn analyze_buffer(buffer: Vec<u8>) -> CharsetMatch {
+    let (encoding_str, skip_bytes) = guess_initial_encoding(&buffer);
+    let has_bom = skip_bytes > 0;
+    let buffer_slice = &buffer[skip_bytes..];
+
+    if encoding_str == "UTF-8" {
+        let (decoded, _, _) = encoding_rs::UTF_8.decode(buffer_slice);
+        let (language, _) = best_language(&decoded);
+        let decoded_text = decoded.to_string();
+        return CharsetMatch {
+            encoding: "utf_8".to_string(),
+            raw_score: i64::MAX,
+            language,
+            chaos: 0.0,
+            coherence: 0.0,
+            submatch: Vec::new(),
+            could_be_from_charset: Vec::new(),
+            has_bom,
+            raw_bytes: buffer,
+            decoded_text,
+        };
+    }
 
-    Ok(CharsetMatch {
-        encoding: normalized_encoding,
+    let encodings_to_try = build_candidate_list(encoding_str);
+    let scored = score_candidates(buffer_slice, encoding_str, &encodings_to_try);
+
+    let Some(best) = scored.first() else {
+        return CharsetMatch {
+            encoding: normalize_encoding_name("UTF-8"),
+            raw_score: i64::MIN,
+            language: String::new(),
+            chaos: 1.0,
+            coherence: 0.0,
+            submatch: Vec::new(),
+            could_be_from_charset: Vec::new(),
+            has_bom,
+            raw_bytes: buffer,
+            decoded_text: String::new(),
+        };
+    };
+
+    let submatch: Vec<String> = scored
+        .iter()
+        .filter(|c| c.name != best.name && c.text == best.text)
+        .map(|c| c.name.clone())
+        .collect();
+    let could_be_from_charset: Vec<String> = scored.iter().skip(1).map(|c| c.name.clone()).collect();
+
+    CharsetMatch {
+        encoding: normalize_final_encoding(&best.name),
+        raw_score: best.raw_score,
+        language: best.language.clone(),
+        chaos: best.mess,
+        coherence: best.coherence,
+        submatch,
+        could_be_from_charset,
+        has_bom,
         raw_bytes: buffer,
-        decoded_text: best_text,
-    })
+        decoded_text: best.text.clone(),
+    }
+}
+
+/// Like `analyze_buffer`, but returns every candidate that survived the mess
+/// filter instead of collapsing to one, so callers can apply their own
+/// disambiguation (e.g. cross-checking against a filename language tag).
+fn analyze_buffer_all(buffer: Vec<u8>) -> Vec<CharsetMatch> {
+    let (encoding_str, skip_bytes) = guess_initial_encoding(&buffer);
+    let has_bom = skip_bytes > 0;
+    let buffer_slice = &buffer[skip_bytes..];
+
+    if encoding_str == "UTF-8" {
+        let (decoded, _, _) = encoding_rs::UTF_8.decode(buffer_slice);
+        let (language, _) = best_language(&decoded);
+        let decoded_text = decoded.to_string();
+        return vec![CharsetMatch {
+            encoding: "utf_8".to_string(),
+            raw_score: i64::MAX,
+            language,
+            chaos: 0.0,
+            coherence: 0.0,
+            submatch: Vec::new(),
+            could_be_from_charset: Vec::new(),
+            has_bom,
+            raw_bytes: buffer,
+            decoded_text,
+        }];
+    }
+
+    let encodings_to_try = build_candidate_list(encoding_str);
+    let scored = score_candidates(buffer_slice, encoding_str, &encodings_to_try);
+
+    // Nothing survived the mess filter: an empty collection, so that
+    // `CharsetMatches::best()` reports `None`.
+    if scored.is_empty() {
+        return Vec::new();
+    }
+
+    scored
+        .iter()
+        .enumerate()
+        .map(|(idx, candidate)| {
+            let submatch: Vec<String> = scored
+                .iter()
+                .filter(|c| c.name != candidate.name && c.text == candidate.text)
+                .map(|c| c.name.clone())
+                .collect();
+            // Only candidates ranked below this one, matching the
+            // `could_be_from_charset` field's documented contract.
+            let could_be_from_charset: Vec<String> = scored[idx + 1..]
+                .iter()
+                .map(|c| c.name.clone())
+                .collect();
+
+            CharsetMatch {
+                encoding: normalize_final_encoding(&candidate.name),
+                raw_score: candidate.raw_score,
+                language: candidate.language.clone(),
+                chaos: candidate.mess,
+                coherence: candidate.coherence,
+                submatch,
+                could_be_from_charset,
+                has_bom,
+                raw_bytes: buffer.clone(),
+                decoded_text: candidate.text.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Detects encoding and language from a file
+#[pyfunction]
+fn from_path(file_path: String) -> PyResult<CharsetMatch> {
+    let path = Path::new(&file_path);
+    let mut file = File::open(path).map_err(|e| {
+        PyIOError::new_err(format!("Failed to open file: {}", e))
+    })?;
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).map_err(|e| {
+        PyIOError::new_err(format!("Failed to read file: {}", e))
+    })?;
+
+    Ok(analyze_buffer(buffer))
 }
 
 /// Detects encoding from a file using streaming (memory efficient for large files)
@@ -433,169 +619,69 @@ fn from_path_stream(file_path: String, max_sample_size: Option<usize>) -> PyResu
         return Err(PyIOError::new_err("File is empty"));
     }
 
-    // Check for BOM markers
-    let (encoding_str, skip_bytes) = if buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
-        ("utf_8", 3)
-    } else if buffer.starts_with(&[0xFF, 0xFE]) {
-        ("UTF-16LE", 2)
-    } else if buffer.starts_with(&[0xFE, 0xFF]) {
-        ("UTF-16BE", 2)
-    } else if buffer.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
-        ("UTF-32LE", 4)
-    } else if buffer.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
-        ("UTF-32BE", 4)
-    } else if let Some(utf16_encoding) = detect_utf16_pattern(&buffer) {
-        (utf16_encoding, 0)
-    } else {
-        let byte_hints = analyze_byte_patterns(&buffer);
-        let result = chardet::detect(&buffer);
-        let detected = result.0.to_lowercase().replace("-", "_");
-
-        let encoding = match detected.as_str() {
-            "utf_8" | "utf8" | "ascii" => "UTF-8",
-            "big5" | "big_5" => "Big5",
-            "gb2312" | "gb_2312" | "gbk" => "GBK",
-            "windows_1252" | "cp1252" | "iso_8859_1" => {
-                if byte_hints.contains(&"likely_turkish") {
-                    "windows-1254"
-                } else {
-                    "windows-1252"
-                }
-            },
-            "windows_1256" | "cp1256" | "iso_8859_6" => "windows-1256",
-            "windows_1255" | "cp1255" | "iso_8859_8" => "windows-1255",
-            "windows_1253" | "cp1253" | "iso_8859_7" => "windows-1253",
-            "windows_1251" | "cp1251" | "iso_8859_5" => {
-                if byte_hints.contains(&"likely_arabic") {
-                    "windows-1256"
-                } else if byte_hints.contains(&"likely_mac_cyrillic") {
-                    "x-mac-cyrillic"
-                } else {
-                    "windows-1251"
-                }
-            },
-            "windows_1254" | "cp1254" | "iso_8859_9" => "windows-1254",
-            "windows_1250" | "cp1250" | "iso_8859_2" => "windows-1250",
-            "euc_kr" | "cp949" | "windows_949" | "ks_c_5601_1987" => "windows-949",
-            "shift_jis" | "shift_jisx0213" | "cp932" => "shift_jis",
-            "euc_jp" => "EUC-JP",
-            "mac_cyrillic" | "x_mac_cyrillic" => "x-mac-cyrillic",
-            "koi8_r" | "koi8r" => "KOI8-R",
-            _ => "UTF-8",
-        };
-        (encoding, 0)
-    };
-
-    let buffer_slice = &buffer[skip_bytes..];
-    let mut encodings_to_try = vec![encoding_str];
-
-    let byte_hints = analyze_byte_patterns(&buffer);
+    Ok(analyze_buffer(buffer))
+}
 
-    for enc in &[
-        "UTF-8",
-        "x-mac-cyrillic",
-        "windows-1252",
-        "windows-1256",
-        "windows-1255",
-        "windows-1253",
-        "windows-1251",
-        "windows-1254",
-        "windows-1250",
-        "windows-949",
-        "Big5",
-        "GBK",
-        "shift_jis",
-        "EUC-JP",
-        "EUC-KR",
-        "mac-cyrillic",
-        "KOI8-R",
-        "ISO-8859-1",
-    ] {
-        if !encodings_to_try.contains(enc) {
-            encodings_to_try.push(enc);
-        }
+/// Detects encoding and language directly from an in-memory buffer, so
+/// callers who already hold bytes (HTTP bodies, DB blobs, subtitle buffers)
+/// don't need to round-trip through a temp file.
+#[pyfunction]
+#[pyo3(signature = (data, max_sample_size=None))]
+fn from_bytes(data: &[u8], max_sample_size: Option<usize>) -> PyResult<CharsetMatch> {
+    if data.is_empty() {
+        return Err(PyIOError::new_err("Buffer is empty"));
     }
 
-    let mut best_encoding = None;
-    let mut best_text = String::new();
-    let mut min_error_ratio = 1.0;
-    let mut best_score = f32::MIN;
-
-    for encoding_name in &encodings_to_try {
-        if let Some(encoding) = encoding_rs::Encoding::for_label(encoding_name.as_bytes()) {
-            let (decoded, _, had_errors) = encoding.decode(buffer_slice);
-
-            let error_chars = decoded.chars().filter(|&c| c == '\u{FFFD}').count();
-            let total_chars = decoded.chars().count().max(1);
-            let error_ratio = error_chars as f32 / total_chars as f32;
-
-            let mut score = 1.0 - error_ratio;
-
-            if encoding_name == &encoding_str {
-                score += 0.05;
-            }
-
-            let lang_hints = detect_language_hints(&decoded);
+    let max_size = max_sample_size.unwrap_or(MAX_SAMPLE_SIZE);
+    let sample_len = data.len().min(max_size);
 
-            if lang_hints.contains(&"arabic") && encoding_name.contains("1256") {
-                score += 0.5;
-            }
-            if lang_hints.contains(&"turkish") && encoding_name.contains("1254") {
-                score += 0.4;
-            }
-            if lang_hints.contains(&"korean") {
-                if encoding_name.contains("949") || encoding_name.contains("windows-949") {
-                    score += 0.4;
-                } else if encoding_name.contains("euc-kr") || encoding_name.contains("EUC-KR") {
-                    score += 0.2;
-                }
-            }
-            if lang_hints.contains(&"cyrillic") {
-                if encoding_name.contains("mac-cyrillic") || encoding_name.contains("x-mac-cyrillic") {
-                    score += 0.5;
-                } else if encoding_name.contains("1251") {
-                    score += 0.2;
-                }
-            }
+    Ok(analyze_buffer(data[..sample_len].to_vec()))
+}
 
-            if lang_hints.contains(&"arabic") && encoding_name.contains("1251") {
-                score -= 0.5;
-            }
-            if lang_hints.contains(&"cyrillic") && encoding_name.contains("1256") {
-                score -= 0.9;
-            }
+/// Like `from_path`, but returns every candidate that survived the mess
+/// filter, ranked best-first, instead of collapsing to the single winner.
+#[pyfunction]
+fn from_path_all(file_path: String) -> PyResult<CharsetMatches> {
+    let path = Path::new(&file_path);
+    let mut file = File::open(path).map_err(|e| {
+        PyIOError::new_err(format!("Failed to open file: {}", e))
+    })?;
 
-            if byte_hints.contains(&"likely_mac_cyrillic") &&
-               (encoding_name.contains("mac-cyrillic") || encoding_name.contains("x-mac-cyrillic")) {
-                score += 0.4;
-            }
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).map_err(|e| {
+        PyIOError::new_err(format!("Failed to read file: {}", e))
+    })?;
 
-            if score > best_score || (score == best_score && error_ratio < min_error_ratio) {
-                best_score = score;
-                min_error_ratio = error_ratio;
-                best_encoding = Some(encoding.name().to_string());
-                best_text = decoded.to_string();
+    Ok(CharsetMatches::new(analyze_buffer_all(buffer)))
+}
 
-                if !had_errors && error_ratio == 0.0 && score > 1.0 {
-                    break;
-                }
-            }
-        }
+/// Like `from_bytes`, but returns every candidate that survived the mess
+/// filter, ranked best-first, instead of collapsing to the single winner.
+#[pyfunction]
+#[pyo3(signature = (data, max_sample_size=None))]
+fn from_bytes_all(data: &[u8], max_sample_size: Option<usize>) -> PyResult<CharsetMatches> {
+    if data.is_empty() {
+        return Err(PyIOError::new_err("Buffer is empty"));
     }
 
-    let mut final_encoding = best_encoding.unwrap_or_else(|| "UTF-8".to_string());
-
-    if final_encoding.to_lowercase().contains("euc-kr") || final_encoding.to_lowercase().contains("euc_kr") {
-        final_encoding = "windows-949".to_string();
-    }
+    let max_size = max_sample_size.unwrap_or(MAX_SAMPLE_SIZE);
+    let sample_len = data.len().min(max_size);
 
-    let normalized_encoding = normalize_encoding_name(&final_encoding);
+    Ok(CharsetMatches::new(analyze_buffer_all(data[..sample_len].to_vec())))
+}
 
-    Ok(CharsetMatch {
-        encoding: normalized_encoding,
-        raw_bytes: buffer, // Apenas a amostra, não o arquivo completo
-        decoded_text: best_text,
-    })
+/// chardet/charset_normalizer-compatible entry point: returns a dict with
+/// `encoding`, `confidence` and `language`, so this crate can be a drop-in
+/// replacement for `chardet.detect` / `charset_normalizer.detect`.
+#[pyfunction]
+fn detect(py: Python<'_>, data: &[u8]) -> PyResult<Py<pyo3::types::PyDict>> {
+    let result = from_bytes(data, None)?;
+
+    let dict = pyo3::types::PyDict::new_bound(py);
+    dict.set_item("encoding", &result.encoding)?;
+    dict.set_item("confidence", (1.0 - result.chaos).clamp(0.0, 1.0))?;
+    dict.set_item("language", &result.language)?;
+    Ok(dict.unbind())
 }
 
 /// A Python module implemented in Rust.
@@ -603,6 +689,93 @@ fn from_path_stream(file_path: String, max_sample_size: Option<usize>) -> PyResu
 fn _internal(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(from_path, m)?)?;
     m.add_function(wrap_pyfunction!(from_path_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(from_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(from_path_all, m)?)?;
+    m.add_function(wrap_pyfunction!(from_bytes_all, m)?)?;
+    m.add_function(wrap_pyfunction!(detect, m)?)?;
     m.add_class::<CharsetMatch>()?;
+    m.add_class::<CharsetMatches>()?;
+    m.add_class::<CharsetMatchesIter>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charset_match(decoded_text: &str) -> CharsetMatch {
+        CharsetMatch {
+            encoding: "utf_8".to_string(),
+            raw_score: 0,
+            language: String::new(),
+            chaos: 0.0,
+            coherence: 0.0,
+            submatch: Vec::new(),
+            could_be_from_charset: Vec::new(),
+            has_bom: false,
+            raw_bytes: decoded_text.as_bytes().to_vec(),
+            decoded_text: decoded_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn bytes_defaults_to_utf8() {
+        let m = charset_match("café");
+        assert_eq!(m.bytes().unwrap(), "café".as_bytes());
+    }
+
+    #[test]
+    fn output_with_no_args_defaults_to_utf8() {
+        let m = charset_match("café");
+        assert_eq!(m.output(None, true, false).unwrap(), "café".as_bytes());
+    }
+
+    #[test]
+    fn output_accepts_python_codec_style_names() {
+        let m = charset_match("café");
+        let encoded = m.output(Some("latin_1".to_string()), true, false).unwrap();
+        assert_eq!(encoded, encoding_rs::WINDOWS_1252.encode("café").0.into_owned());
+    }
+
+    // End-to-end round-trips through the real `analyze_buffer` pipeline
+    // (build_candidate_list -> score_candidates -> sort), not just the
+    // isolated functions tested in `scoring` and `mess`. These are what caught the
+    // windows-1251/koi8_r and cp1252/koi8_r misdetections.
+
+    #[test]
+    fn cyrillic_windows_1251_is_not_misdetected_as_koi8_r() {
+        let text = "Привет, как дела? Это тестовое сообщение на русском языке.";
+        let (encoded, _, _) = encoding_rs::WINDOWS_1251.encode(text);
+        let result = analyze_buffer(encoded.into_owned());
+        assert_eq!(result.encoding, "cp1251");
+        assert_eq!(result.decoded_text, text);
+    }
+
+    #[test]
+    fn western_european_cp1252_is_not_misdetected_as_koi8_r() {
+        let text = "café à Noël, vive la France et le réveillon !";
+        let (encoded, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+        let result = analyze_buffer(encoded.into_owned());
+        assert_eq!(result.encoding, "cp1252");
+        assert_eq!(result.decoded_text, text);
+    }
+
+    #[test]
+    fn genuine_koi8_r_text_is_still_detected_as_koi8_r() {
+        let text = "Привет, как дела? Это тестовое сообщение на русском языке.";
+        let (encoded, _, _) = encoding_rs::KOI8_R.encode(text);
+        let result = analyze_buffer(encoded.into_owned());
+        assert_eq!(result.encoding, "koi8_r");
+        assert_eq!(result.decoded_text, text);
+    }
+
+    #[test]
+    fn iso_2022_jp_japanese_text_is_not_penalized_for_its_own_script() {
+        let text = "こんにちは、これはテストです。";
+        let (encoded, _, _) = encoding_rs::ISO_2022_JP.encode(text);
+        let result = analyze_buffer(encoded.into_owned());
+        assert_eq!(result.encoding, "iso2022_jp");
+        assert_eq!(result.decoded_text, text);
+        assert_eq!(result.chaos, 0.0);
+    }
+}
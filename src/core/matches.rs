@@ -0,0 +1,69 @@
+// Ranked collection of `CharsetMatch` candidates, mirroring the real
+// charset-normalizer `CharsetMatches` API so callers who want to apply their
+// own disambiguation (e.g. cross-checking a filename language tag) aren't
+// forced to accept the single winner `from_path`/`from_bytes` collapse to.
+
+use pyo3::exceptions::PyIndexError;
+use pyo3::prelude::*;
+
+use crate::CharsetMatch;
+
+#[pyclass]
+pub(crate) struct CharsetMatches {
+    matches: Vec<CharsetMatch>,
+}
+
+impl CharsetMatches {
+    /// `matches` must already be sorted best-first; this type doesn't
+    /// re-rank, it just exposes what `analyze_buffer_all` produced.
+    pub(crate) fn new(matches: Vec<CharsetMatch>) -> Self {
+        Self { matches }
+    }
+}
+
+#[pymethods]
+impl CharsetMatches {
+    /// The best candidate, or `None` if nothing survived the mess filter.
+    fn best(&self) -> Option<CharsetMatch> {
+        self.matches.first().cloned()
+    }
+
+    fn __len__(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn __getitem__(&self, index: usize) -> PyResult<CharsetMatch> {
+        self.matches
+            .get(index)
+            .cloned()
+            .ok_or_else(|| PyIndexError::new_err("CharsetMatches index out of range"))
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyResult<Py<CharsetMatchesIter>> {
+        let iter = CharsetMatchesIter { matches: slf.matches.clone(), index: 0 };
+        Py::new(slf.py(), iter)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<CharsetMatches {} candidate(s)>", self.matches.len())
+    }
+}
+
+#[pyclass]
+pub(crate) struct CharsetMatchesIter {
+    matches: Vec<CharsetMatch>,
+    index: usize,
+}
+
+#[pymethods]
+impl CharsetMatchesIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<CharsetMatch> {
+        let next = self.matches.get(self.index)?.clone();
+        self.index += 1;
+        Some(next)
+    }
+}
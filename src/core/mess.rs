@@ -0,0 +1,170 @@
+// Mess ("chaos") ratio: counts structurally suspicious characters and
+// transitions in already-decoded text, catching decodings that produce no
+// U+FFFD but are nonetheless garbage (a wrong-but-valid single-byte mapping).
+// Adapted from the charset-normalizer approach of many small independent
+// "mess" detectors summed into one ratio, rather than the per-language
+// `score += 0.x` bonuses this crate used to hand-tune per encoding.
+
+/// Candidates above this ratio are rejected outright.
+pub const MESS_THRESHOLD: f32 = 0.20;
+
+fn is_accented_latin(c: char) -> bool {
+    let code = c as u32;
+    c.is_alphabetic() && ((0x00C0..=0x024F).contains(&code) || (0x1E00..=0x1EFF).contains(&code))
+}
+
+fn script_of(c: char) -> Option<&'static str> {
+    let code = c as u32;
+    match code {
+        0x0041..=0x005A | 0x0061..=0x007A => Some("latin"),
+        0x00C0..=0x024F | 0x1E00..=0x1EFF => Some("latin"),
+        0x0400..=0x04FF => Some("cyrillic"),
+        0x0370..=0x03FF => Some("greek"),
+        0x0600..=0x06FF => Some("arabic"),
+        _ => None,
+    }
+}
+
+/// Compute the mess ratio of already-decoded `text`, in `[0, 1]`.
+pub fn mess_ratio(text: &str) -> f32 {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let mut suspicious = 0usize;
+    let mut accented_run = 0usize;
+    let mut prev_script: Option<&'static str> = None;
+    let mut prev_case_is_lower = false;
+
+    for (i, &c) in chars.iter().enumerate() {
+        let mut is_suspicious = false;
+
+        // C0/C1 control characters other than tab/newline/CR.
+        if c.is_control() && !matches!(c, '\t' | '\n' | '\r') {
+            is_suspicious = true;
+        }
+
+        // A letter immediately followed by a symbol/currency sign with no space.
+        if c.is_alphabetic() {
+            if let Some(&next) = chars.get(i + 1) {
+                if is_symbol_or_currency(next) && !next.is_whitespace() {
+                    is_suspicious = true;
+                }
+            }
+        }
+
+        // Runs of 3+ accented Latin letters in a row.
+        if is_accented_latin(c) {
+            accented_run += 1;
+            if accented_run >= 3 {
+                is_suspicious = true;
+            }
+        } else {
+            accented_run = 0;
+        }
+
+        // Mixing of distinct scripts within one word (no whitespace between them).
+        if let Some(script) = script_of(c) {
+            if let Some(prev) = prev_script {
+                if prev != script {
+                    is_suspicious = true;
+                }
+            }
+            prev_script = Some(script);
+        } else if c.is_whitespace() || c.is_ascii_punctuation() {
+            prev_script = None;
+        }
+
+        // Isolated combining marks with no base character before them.
+        if unicode_is_combining_mark(c) && (i == 0 || chars[i - 1].is_whitespace()) {
+            is_suspicious = true;
+        }
+
+        // Unusual case transitions: lowercase -> uppercase mid-token.
+        if c.is_alphabetic() {
+            let is_upper = c.is_uppercase();
+            if i > 0 && prev_case_is_lower && is_upper && !chars[i - 1].is_whitespace() {
+                is_suspicious = true;
+            }
+            prev_case_is_lower = !is_upper;
+        } else {
+            prev_case_is_lower = false;
+        }
+
+        if is_suspicious {
+            suspicious += 1;
+        }
+    }
+
+    suspicious as f32 / chars.len() as f32
+}
+
+fn unicode_is_combining_mark(c: char) -> bool {
+    let code = c as u32;
+    (0x0300..=0x036F).contains(&code) // Combining Diacritical Marks
+}
+
+/// Approximates the Unicode `Sc`/`Sk`/`Sm`/`So` symbol categories by range,
+/// since `char` has no `is_symbol` in std. Covers the currency, math and
+/// misc-symbol blocks that actually show up in mis-decoded text.
+fn is_symbol_or_currency(c: char) -> bool {
+    let code = c as u32;
+    matches!(c, '$' | '+' | '<' | '=' | '>' | '^' | '`' | '|' | '~')
+        || matches!(code, 0x00A2..=0x00A6 | 0x00A8 | 0x00A9 | 0x00AC | 0x00AE | 0x00AF | 0x00B0 | 0x00B1 | 0x00B4 | 0x00B8 | 0x00D7 | 0x00F7)
+        || (0x20A0..=0x20CF).contains(&code) // currency symbols
+        || (0x2100..=0x214F).contains(&code) // letterlike symbols
+        || (0x2190..=0x2BFF).contains(&code) // arrows, math operators, misc symbols, dingbats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_ascii_text_has_no_mess() {
+        assert_eq!(mess_ratio("The quick brown fox jumps over the lazy dog."), 0.0);
+    }
+
+    #[test]
+    fn empty_text_has_no_mess() {
+        assert_eq!(mess_ratio(""), 0.0);
+    }
+
+    #[test]
+    fn control_characters_are_suspicious() {
+        assert!(mess_ratio("hello\u{0001}world") > 0.0);
+    }
+
+    #[test]
+    fn tab_newline_and_cr_are_not_suspicious() {
+        assert_eq!(mess_ratio("line one\nline two\ttabbed\r\n"), 0.0);
+    }
+
+    #[test]
+    fn mixed_scripts_within_a_word_are_suspicious() {
+        // Latin 'a' immediately followed by Cyrillic 'б' with no whitespace.
+        assert!(mess_ratio("aб") > 0.0);
+    }
+
+    #[test]
+    fn mixed_scripts_across_words_are_not_suspicious() {
+        assert_eq!(mess_ratio("hello мир"), 0.0);
+    }
+
+    #[test]
+    fn isolated_combining_mark_is_suspicious() {
+        // U+0301 COMBINING ACUTE ACCENT with nothing before it to combine with.
+        assert!(mess_ratio("\u{0301}abc") > 0.0);
+    }
+
+    #[test]
+    fn combining_mark_after_a_base_letter_is_fine() {
+        assert_eq!(mess_ratio("e\u{0301}cole"), 0.0);
+    }
+
+    #[test]
+    fn long_run_of_accented_latin_is_suspicious() {
+        assert!(mess_ratio("àáâãäåæçèé") > 0.0);
+    }
+}
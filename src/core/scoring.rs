@@ -0,0 +1,218 @@
+// Candidate-scoring engine modeled on chardetng: instead of only counting
+// replacement characters, walk the decoded text once and score letter-class
+// transitions. A running i64 score per candidate lets structurally implausible
+// decodings (e.g. windows-1251 read as windows-1256) lose even when both
+// produce a clean, error-free decode.
+
+/// Penalty applied when an ASCII Latin letter sits immediately next to a
+/// non-ASCII Latin letter or a letter from another script entirely (Cyrillic,
+/// Greek, Arabic, ...), which almost never happens mid-word in real text.
+const LATIN_ADJACENCY_PENALTY: i64 = -50;
+
+/// Penalty for a control character or a byte that maps to an implausible /
+/// unused slot in the candidate encoding.
+const IMPLAUSIBILITY_PENALTY: i64 = -220;
+
+/// Bonus when an uppercase non-Latin letter (Cyrillic, Greek, Arabic, ...)
+/// starts a run, matching normal capitalization of sentences/names.
+const NON_LATIN_CAPITALIZATION_BONUS: i64 = 60;
+
+/// Bonus for a plausible digit/ordinal run (dates, numbered lists, prices).
+const ORDINAL_BONUS: i64 = 300;
+
+/// Bonus for an isolated symbol like a copyright sign, which is common in
+/// real text and otherwise looks like "other" noise.
+const COPYRIGHT_BONUS: i64 = 30;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CharClass {
+    AsciiLatin,
+    NonAsciiLatin,
+    NonLatinAlphabetic,
+    Cjk,
+    PunctuationOrSpace,
+    Other,
+}
+
+fn classify(c: char) -> CharClass {
+    if c.is_control() {
+        return CharClass::Other;
+    }
+    if c.is_ascii_alphabetic() {
+        return CharClass::AsciiLatin;
+    }
+
+    let code = c as u32;
+    let is_cjk = (0x3040..=0x30FF).contains(&code) // Hiragana/Katakana
+        || (0x4E00..=0x9FFF).contains(&code) // CJK Unified Ideographs
+        || (0xAC00..=0xD7AF).contains(&code) // Hangul Syllables
+        || (0xF900..=0xFAFF).contains(&code); // CJK Compatibility Ideographs
+    if is_cjk {
+        return CharClass::Cjk;
+    }
+
+    if c.is_alphabetic() {
+        let is_latin_extended = (0x00C0..=0x024F).contains(&code) || (0x1E00..=0x1EFF).contains(&code);
+        if is_latin_extended {
+            return CharClass::NonAsciiLatin;
+        }
+        return CharClass::NonLatinAlphabetic;
+    }
+
+    if c.is_whitespace() || c.is_ascii_punctuation() {
+        return CharClass::PunctuationOrSpace;
+    }
+
+    CharClass::Other
+}
+
+/// Score how plausible already-decoded `text` looks.
+///
+/// Higher is better. Each unmappable sequence (rendered by `encoding_rs` as
+/// U+FFFD) is penalized like any other implausible byte rather than
+/// rejecting the whole candidate outright, so a single bad lead/trail byte
+/// in an otherwise-clean multi-byte decode scores it down instead of killing
+/// it outright the way the old binary accept/reject heuristic did. Takes
+/// already-decoded text, rather than an `encoding_rs::Encoding` plus raw
+/// bytes, so that candidates like HZ-GB2312 which don't have an
+/// `encoding_rs` codec to decode through can be scored the same way.
+pub fn score_text(text: &str) -> i64 {
+    let mut score: i64 = 0;
+    let mut prev_class: Option<CharClass> = None;
+    // True only at the very start of the text or right after a sentence
+    // terminator, not after every space/comma, so the capitalization bonus
+    // below can't fire once per word in a run of mojibake.
+    let mut sentence_boundary = true;
+    let mut digit_run = 0usize;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let class = classify(c);
+
+        // An unmappable sequence (rendered as U+FFFD) is just as implausible
+        // as a stray control character, so both share one penalty.
+        if c == '\u{FFFD}' || (c.is_control() && !matches!(c, '\t' | '\n' | '\r')) {
+            score += IMPLAUSIBILITY_PENALTY;
+        }
+
+        if let Some(prev) = prev_class {
+            let latin_boundary = (prev == CharClass::AsciiLatin && class == CharClass::NonAsciiLatin)
+                || (prev == CharClass::NonAsciiLatin && class == CharClass::AsciiLatin)
+                || (prev == CharClass::AsciiLatin && class == CharClass::NonLatinAlphabetic)
+                || (prev == CharClass::NonLatinAlphabetic && class == CharClass::AsciiLatin);
+            if latin_boundary {
+                score += LATIN_ADJACENCY_PENALTY;
+            }
+        }
+
+        if class == CharClass::NonLatinAlphabetic && sentence_boundary && c.is_uppercase() {
+            score += NON_LATIN_CAPITALIZATION_BONUS;
+        }
+
+        if c.is_ascii_digit() {
+            digit_run += 1;
+        } else {
+            if digit_run >= 2 {
+                score += ORDINAL_BONUS;
+            }
+            digit_run = 0;
+        }
+
+        if matches!(c, '©' | '®' | '™' | '§' | '¶') {
+            let prev_is_boundary = prev_class.is_none_or(|p| p == CharClass::PunctuationOrSpace);
+            let next_is_boundary = chars.peek().is_none_or(|n| classify(*n) == CharClass::PunctuationOrSpace);
+            if prev_is_boundary && next_is_boundary {
+                score += COPYRIGHT_BONUS;
+            }
+        }
+
+        match c {
+            '.' | '!' | '?' | '\n' => sentence_boundary = true,
+            _ if c.is_whitespace() => {}
+            _ => sentence_boundary = false,
+        }
+        prev_class = Some(class);
+    }
+
+    if digit_run >= 2 {
+        score += ORDINAL_BONUS;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_text_beats_text_with_replacement_characters() {
+        let clean = score_text("Hello, world! This is a perfectly ordinary sentence.");
+        let with_replacements = score_text("Hello, w\u{FFFD}rld! Th\u{FFFD}s is bro\u{FFFD}en.");
+        assert!(clean > with_replacements);
+    }
+
+    #[test]
+    fn replacement_characters_are_penalized_per_occurrence_not_rejected_outright() {
+        // A single stray replacement character in an otherwise clean decode
+        // should score down, not collapse to a single all-or-nothing reject.
+        let clean = score_text("This paragraph is almost entirely clean text except one byte.");
+        let one_bad_byte = score_text("This paragraph is almost entirely clean text except one\u{FFFD}byte.");
+        let two_bad_bytes = score_text("This paragraph is almost entirely clean text except one\u{FFFD}\u{FFFD}byte.");
+        assert!(one_bad_byte > i64::MIN / 2);
+        assert!(clean > one_bad_byte);
+        assert!(one_bad_byte > two_bad_bytes);
+    }
+
+    #[test]
+    fn ascii_latin_next_to_non_ascii_latin_is_penalized() {
+        let boundary = score_text("café");
+        let no_boundary = score_text("cafe");
+        assert!(boundary < no_boundary);
+    }
+
+    #[test]
+    fn capitalized_non_latin_word_start_gets_a_bonus() {
+        let capitalized = score_text("Привет");
+        let lowercase = score_text("привет");
+        assert!(capitalized > lowercase);
+    }
+
+    #[test]
+    fn ascii_latin_next_to_cyrillic_is_penalized() {
+        // Mid-word ASCII/Cyrillic mixing should be penalized just like the
+        // ASCII/accented-Latin boundary is.
+        let mixed = score_text("этоAтест");
+        let clean = score_text("этотест");
+        assert!(mixed < clean);
+    }
+
+    #[test]
+    fn capitalization_bonus_does_not_fire_mid_sentence() {
+        // A capital Cyrillic letter after a plain space (not a sentence
+        // terminator) shouldn't collect the sentence-start bonus over and
+        // over in a run of mojibake words.
+        let mid_sentence = score_text("привет Как дела");
+        let sentence_start = score_text("Привет как дела");
+        assert!(sentence_start > mid_sentence);
+    }
+
+    #[test]
+    fn digit_runs_get_an_ordinal_bonus() {
+        let with_digits = score_text("Born in 1987, room 42B.");
+        let without_digits = score_text("Born then, room later.");
+        assert!(with_digits > without_digits);
+    }
+
+    #[test]
+    fn isolated_copyright_symbol_gets_a_bonus() {
+        let with_copyright = score_text("Acme Corp \u{A9} 2024");
+        let without = score_text("Acme Corp X 2024");
+        assert!(with_copyright > without);
+    }
+
+    #[test]
+    fn empty_text_scores_zero() {
+        assert_eq!(score_text(""), 0);
+    }
+}